@@ -0,0 +1,103 @@
+//! Maps a raw directory tree onto cross-validation folds and class labels using real `Path`
+//!  APIs, so a corpus isn't bound to a literal Windows-style `partN\` path segment match.
+//!  The default layout still matches the real PU123A corpora
+//!  (http://www.aueb.gr/users/ion/data/PU123ACorpora.tar.gz), where documents sit directly in
+//!  `partN/` and the class is encoded as a filename prefix (`spmsgC12.txt`, `legit7.txt`), not a
+//!  directory named after the class; a directory-per-class layout (`spam/`, `legit/`) is matched
+//!  too, for corpora organized that way instead.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Configures which directory names (or filename prefixes) name a class, which directories to
+///  skip entirely, and how fold directories are named.
+pub struct CorpusLayout {
+	/// Directory names that directly name a class, e.g. `["spam", "legit"]`.
+	pub class_dirs: Vec<String>,
+	/// Filename prefixes that name a class, e.g. `{"spam": "spmsg", "legit": "legit"}`, for
+	///  corpora (like PU123A) that encode the class in the filename rather than a directory.
+	pub class_file_prefixes: HashMap<String, String>,
+	/// Directory names to exclude from training and evaluation entirely, e.g. `["unused"]`.
+	pub exclude_dirs: HashSet<String>,
+	/// Prefix shared by fold directories, e.g. `"part"` for `part1`, `part2`, ...
+	pub fold_prefix: String
+}
+
+impl Default for CorpusLayout {
+	fn default() -> Self {
+		CorpusLayout {
+			class_dirs: vec!["spam".to_string(), "legit".to_string()],
+			class_file_prefixes: vec![("spam".to_string(), "spmsg".to_string()), ("legit".to_string(), "legit".to_string())]
+				.into_iter().collect(),
+			exclude_dirs: vec!["unused".to_string()].into_iter().collect(),
+			fold_prefix: "part".to_string()
+		}
+	}
+}
+
+impl CorpusLayout {
+	/// Returns the class label for `doc`: the first path component that exactly matches one of
+	///  `class_dirs`, falling back to a `class_file_prefixes` entry whose prefix matches `doc`'s
+	///  file name (when several prefixes match, which one wins is not defined). Returns `None` if
+	///  neither matches.
+	pub fn label_for(&self, doc: &Path) -> Option<String> {
+		let by_dir = doc.components()
+			.filter_map(|c| c.as_os_str().to_str())
+			.find(|name| self.class_dirs.iter().any(|c| c == name))
+			.map(|name| name.to_string());
+		if by_dir.is_some() {
+			return by_dir;
+		}
+
+		let file_name = doc.file_name()?.to_str()?;
+		self.class_file_prefixes.iter()
+			.find(|(_, prefix)| file_name.starts_with(prefix.as_str()))
+			.map(|(label, _)| label.clone())
+	}
+
+	/// Returns true if `doc` lives under the fold directory numbered `fold` (e.g. `"part3"`).
+	pub fn in_fold(&self, doc: &Path, fold: usize) -> bool {
+		let fold_name = format!("{}{}", self.fold_prefix, fold);
+		doc.components().any(|c| c.as_os_str() == fold_name.as_str())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::path::PathBuf;
+
+	/// A PU123A-shaped tree: documents sit directly in `partN/`, with the class encoded as a
+	///  filename prefix rather than a directory.
+	fn pu123a_layout() -> CorpusLayout {
+		CorpusLayout::default()
+	}
+
+	#[test]
+	fn label_for_matches_pu123a_filename_prefixes() {
+		let layout = pu123a_layout();
+		assert_eq!(layout.label_for(&PathBuf::from("corpus/part1/spmsgC12.txt")), Some("spam".to_string()));
+		assert_eq!(layout.label_for(&PathBuf::from("corpus/part1/legit7.txt")), Some("legit".to_string()));
+	}
+
+	#[test]
+	fn label_for_matches_directory_per_class_layout() {
+		let layout = pu123a_layout();
+		assert_eq!(layout.label_for(&PathBuf::from("corpus/spam/1.txt")), Some("spam".to_string()));
+		assert_eq!(layout.label_for(&PathBuf::from("corpus/legit/1.txt")), Some("legit".to_string()));
+	}
+
+	#[test]
+	fn label_for_returns_none_outside_any_class() {
+		let layout = pu123a_layout();
+		assert_eq!(layout.label_for(&PathBuf::from("corpus/part1/readme.txt")), None);
+	}
+
+	#[test]
+	fn in_fold_matches_the_numbered_fold_directory_only() {
+		let layout = pu123a_layout();
+		let doc = PathBuf::from("corpus/part3/spmsgC1.txt");
+		assert!(layout.in_fold(&doc, 3));
+		assert!(!layout.in_fold(&doc, 4));
+	}
+}
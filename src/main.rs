@@ -1,12 +1,21 @@
-/// kesnar-naive Bayes (Knaive-Bayes) is a simple implementation of the Naive Bayes algorithm in Rust
-/// Written by kesnar (Panagiotis Famelis) in December 2020
-/// Published under CC BY-NC-SA 4.0 (Attribution-NonCommercial-ShareAlike 4.0 International)
+//! kesnar-naive Bayes (Knaive-Bayes) is a simple implementation of the Naive Bayes algorithm in Rust
+//! Written by kesnar (Panagiotis Famelis) in December 2020
+//! Published under CC BY-NC-SA 4.0 (Attribution-NonCommercial-ShareAlike 4.0 International)
+
+mod corpus;
+mod scan;
+mod tokenizer;
 
 use std::{fs, env};
 use std::path::{Path, PathBuf};
 use std::error::Error;
 use core::hash::{Hasher, BuildHasherDefault};
 use std::collections::{HashMap, HashSet};
+use serde::{Serialize, Deserialize};
+use rayon::prelude::*;
+use corpus::CorpusLayout;
+use scan::{ScanOptions, visit_dirs};
+use tokenizer::{Vocabulary, tokenize};
 
 /// The identityHasher is the identity function f(x) = x.
 /// The identity hash has been selected for speed reasons, as the Hash is used instead of a sparse Vector
@@ -30,29 +39,32 @@ impl Hasher for IdentityHasher {
 type BuildIdentityHasher = BuildHasherDefault<IdentityHasher>;
 
 /// A struct for the collection of the probabilities needed for the Naive Bayes Classification.
+/// Generalized to an arbitrary number of classes: each class has a prior and a per-word
+///  probability map, keyed by the class label.
+#[derive(Serialize, Deserialize)]
 struct NaiveBayesProbabilities {
-	spam: f64,
-	legit: f64,
-	word_spam: HashMap<u32, f64, BuildIdentityHasher>,
-	word_legit: HashMap<u32, f64, BuildIdentityHasher>
+	priors: HashMap<String, f64>,
+	word_probs: HashMap<String, HashMap<u32, f64, BuildIdentityHasher>>,
+	/// Only populated when the model was trained on raw text; empty for models trained on
+	///  corpora that are already encoded into numeric word ids.
+	#[serde(default)]
+	vocabulary: Vocabulary
 }
 
-/// Recursive function to traverse the directorirs below a directory in a filesystem.
-/// Returns error in case something went wrong.
-fn visit_dirs(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
-	let mut ret = Vec::new();
-	if dir.is_dir() {
-		for entry in fs::read_dir(dir)? {
-			let entry = entry?;
-			let path = entry.path();
-			if path.is_dir() {
-				ret.append(&mut visit_dirs(&path)?);
-			} else {
-				ret.push(path);
-			}
-		}
+impl NaiveBayesProbabilities {
+	/// Serializes the trained model to a JSON file, so it can be reloaded without retraining.
+	fn save_to_json(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+		let file = fs::File::create(path)?;
+		serde_json::to_writer_pretty(file, self)?;
+		Ok(())
+	}
+
+	/// Loads a previously trained model from a JSON file written by `save_to_json`.
+	fn load_from_json(path: &Path) -> Result<Self, Box<dyn Error>> {
+		let file = fs::File::open(path)?;
+		let p = serde_json::from_reader(file)?;
+		Ok(p)
 	}
-	Ok(ret)
 }
 
 /// Function that opens and reads a file, returning a vector with the words in u32.
@@ -66,184 +78,589 @@ fn file_to_array(filename: &PathBuf) -> Result<Vec<u32>, Box<dyn Error>> {
     Ok(ret)
 }
 
-/// The function that calculates the Naive Bayes probabilities.
-/// It takes as input a directory containing the data set, 
-///  in format as described in http://www.aueb.gr/users/ion/data/PU123ACorpora.tar.gz
-/// In case of error in some files, it disregards that file, prints and error message 
-///  and continues to the next.
-fn learn_naive_bayes(data_set: Vec<PathBuf>) -> NaiveBayesProbabilities {
-	let mut total = 0;
-	let mut spam = 0;
-	let mut legit = 0;
-
-	let mut n_spam = 0;
-	let mut n_legit = 0;
-
-	let mut word_set: HashSet<u32, BuildIdentityHasher> = HashSet::default();
-	let mut occurences_spam: HashMap<u32, u32, BuildIdentityHasher> = HashMap::default();
-	let mut occurences_legit: HashMap<u32, u32, BuildIdentityHasher> = HashMap::default();
-
-	for doc in data_set.iter() {
-		match file_to_array(doc) {
-			Ok(example) => {
-				let is_spam;
-				total += 1;
-				if doc.as_path().display().to_string().contains("spmsg") {
-					spam += 1;
-					is_spam = true;
-				}
-				else {//if doc.as_path().display().to_string().contains("legit") {
-					legit += 1;
-					is_spam = false;
-				}
-				for word in example {
-					word_set.insert(word);
-					if is_spam {
-						// Increment the occurences by 1 or insert a new entry for the word with 1 occurence
-						*occurences_spam.entry(word).or_insert(1) += 1;
-						n_spam += 1;
-					}
-					else {
-						// Increment the occurences by 1 or insert a new entry for the word with 1 occurence
-						*occurences_legit.entry(word).or_insert(1) += 1;
-						n_legit += 1;
-					}
-				}
-			}
-			Err(e) => println!("{}", e)
+/// Per-worker accumulator folded over chunks of the data set in parallel, then merged pairwise
+///  via `merge` into the final counts `learn_naive_bayes_from_ids` turns into probabilities.
+struct Counts {
+	total: u32,
+	class_counts: HashMap<String, u32>,
+	class_token_counts: HashMap<String, u32>,
+	word_set: HashSet<u32, BuildIdentityHasher>,
+	occurences: HashMap<String, HashMap<u32, u32, BuildIdentityHasher>>
+}
+
+impl Counts {
+	fn new() -> Self {
+		Counts {
+			total: 0,
+			class_counts: HashMap::new(),
+			class_token_counts: HashMap::new(),
+			word_set: HashSet::default(),
+			occurences: HashMap::new()
+		}
+	}
+
+	/// Folds one document's worth of word ids into this accumulator, skipping documents to
+	///  which `layout.label_for` assigns no class.
+	fn add(mut self, doc: &Path, example: &[u32], layout: &CorpusLayout) -> Self {
+		let label = match layout.label_for(doc) {
+			Some(label) => label,
+			None => { println!("{}: not under a known class directory, skipping", doc.display()); return self; }
+		};
+		self.total += 1;
+		*self.class_counts.entry(label.clone()).or_insert(0) += 1;
+		let class_occurences = self.occurences.entry(label.clone()).or_default();
+		for &word in example {
+			self.word_set.insert(word);
+			// Increment the occurences by 1 or insert a new entry for the word with 1 occurence
+			*class_occurences.entry(word).or_insert(0) += 1;
 		}
+		*self.class_token_counts.entry(label).or_insert(0) += example.len() as u32;
+		self
 	}
 
-	let p_spam = spam as f64 / total as f64;
-	let p_legit = legit as f64 / total as f64;
-
-	let spam_divisor = (n_spam + word_set.len()) as f64;
-	let legit_divisor= (n_legit + word_set.len()) as f64;
-	
-	let mut p_word_spam: HashMap<u32, f64, BuildIdentityHasher> = HashMap::default();
-	let mut p_word_legit: HashMap<u32, f64, BuildIdentityHasher> = HashMap::default();
-	
-	for word in word_set {
-		if let Some(x) = occurences_spam.get(&word) {
-			p_word_spam.insert(word, (1 + x) as f64 / spam_divisor);
-		} else {
-			p_word_spam.insert(word, 1.0 / spam_divisor);
+	/// Merges another worker's accumulator into this one.
+	fn merge(mut self, other: Counts) -> Self {
+		self.total += other.total;
+		for (label, count) in other.class_counts {
+			*self.class_counts.entry(label).or_insert(0) += count;
+		}
+		for (label, count) in other.class_token_counts {
+			*self.class_token_counts.entry(label).or_insert(0) += count;
 		}
+		self.word_set.extend(other.word_set);
+		for (label, words) in other.occurences {
+			let class_occurences = self.occurences.entry(label).or_default();
+			for (word, count) in words {
+				*class_occurences.entry(word).or_insert(0) += count;
+			}
+		}
+		self
+	}
+}
+
+/// Accumulates per-class occurrence counts over already-encoded `(path, word ids)` pairs and
+///  computes the smoothed Naive Bayes probabilities. Shared by `learn_naive_bayes` (ids already
+///  assigned by the corpus) and `learn_naive_bayes_text` (ids freshly interned by a `Vocabulary`).
+/// Documents to which `layout.label_for` assigns no class are skipped. The fold
+///  itself runs across the file set in parallel via rayon, then the per-worker counts are merged.
+fn learn_naive_bayes_from_ids(data_set: Vec<(PathBuf, Vec<u32>)>, layout: &CorpusLayout) -> NaiveBayesProbabilities {
+	let Counts { total, class_counts, class_token_counts, word_set, occurences } = data_set.par_iter()
+		.fold(Counts::new, |acc, (doc, example)| acc.add(doc, example, layout))
+		.reduce(Counts::new, Counts::merge);
+
+	let mut priors: HashMap<String, f64> = HashMap::new();
+	for (label, count) in class_counts.iter() {
+		priors.insert(label.clone(), *count as f64 / total as f64);
+	}
 
-		if let Some(x) = occurences_legit.get(&word) {
-			p_word_legit.insert(word, (1 + x) as f64 / legit_divisor);
-		} else {
-			p_word_legit.insert(word, 1.0 / legit_divisor);
+	let mut word_probs: HashMap<String, HashMap<u32, f64, BuildIdentityHasher>> = HashMap::new();
+	for label in class_counts.keys() {
+		let divisor = (*class_token_counts.get(label).unwrap_or(&0) + word_set.len() as u32) as f64;
+		let class_occurences = occurences.get(label);
+
+		let mut p_word: HashMap<u32, f64, BuildIdentityHasher> = HashMap::default();
+		for &word in word_set.iter() {
+			if let Some(x) = class_occurences.and_then(|m| m.get(&word)) {
+				p_word.insert(word, (1 + x) as f64 / divisor);
+			} else {
+				p_word.insert(word, 1.0 / divisor);
+			}
 		}
+		word_probs.insert(label.clone(), p_word);
 	}
 
-	NaiveBayesProbabilities{spam: p_spam, legit: p_legit, word_spam: p_word_spam, word_legit: p_word_legit}
+	NaiveBayesProbabilities{priors, word_probs, vocabulary: Vocabulary::default()}
 }
 
-/// Function that takes a filename and the Naive Bayes Probabilities and clasifies it as spam or not (boolean).
-/// Returns error in case something went wrong.
+/// The function that calculates the Naive Bayes probabilities for an arbitrary number of classes.
+/// It takes as input a directory containing the data set,
+///  in format as described in http://www.aueb.gr/users/ion/data/PU123ACorpora.tar.gz
+/// In case of error in some files, it disregards that file, prints and error message
+///  and continues to the next. Files are read and parsed across the file set in parallel.
+fn learn_naive_bayes(data_set: Vec<PathBuf>, layout: &CorpusLayout) -> NaiveBayesProbabilities {
+	let encoded: Vec<(PathBuf, Vec<u32>)> = data_set.into_par_iter()
+		.filter_map(|doc| match file_to_array(&doc) {
+			Ok(ids) => Some((doc, ids)),
+			Err(e) => { println!("{}", e); None }
+		})
+		.collect();
+
+	learn_naive_bayes_from_ids(encoded, layout)
+}
+
+/// Like `learn_naive_bayes`, but for raw text documents rather than ones already encoded into
+///  numeric word ids: each document is tokenized with `tokenizer::tokenize` across the file set
+///  in parallel, then the resulting words are interned into a `Vocabulary` sequentially (interning
+///  assigns ids, so it can't itself be parallelized), which is persisted alongside the model so
+///  classification can encode new documents the same way.
+fn learn_naive_bayes_text(data_set: Vec<PathBuf>, layout: &CorpusLayout, stop_words: &HashSet<String>) -> NaiveBayesProbabilities {
+	let tokenized: Vec<(PathBuf, Vec<String>)> = data_set.into_par_iter()
+		.filter_map(|doc| match fs::read_to_string(&doc) {
+			Ok(text) => Some((doc, tokenize(&text, stop_words))),
+			Err(e) => { println!("{}", e); None }
+		})
+		.collect();
+
+	let mut vocabulary = Vocabulary::new();
+	let encoded: Vec<(PathBuf, Vec<u32>)> = tokenized.into_iter()
+		.map(|(doc, words)| {
+			let ids = words.iter().map(|w| vocabulary.intern(w)).collect();
+			(doc, ids)
+		})
+		.collect();
+
+	let mut probabilities = learn_naive_bayes_from_ids(encoded, layout);
+	probabilities.vocabulary = vocabulary;
+	probabilities
+}
+
+/// Classifies an already-encoded document, returning the arg-max class label.
 /// As propabilities are < 1, multiplying them results on really small numbers close to zero, that are not
 ///  handled well. As such logarithms are used and the multiplication is trasformed to a sum of log10.
-fn classified_as_spam(filename: &PathBuf, p: &NaiveBayesProbabilities) -> Result<bool, Box<dyn Error>> {
-	match file_to_array(filename) {
-		Ok(doc) => {
-			let mut spam = 0.0;
-			let mut legit = 0.0;
+fn classify_ids(doc: &[u32], p: &NaiveBayesProbabilities) -> Result<String, Box<dyn Error>> {
+	let mut best: Option<(String, f64)> = None;
+	for (label, prior) in p.priors.iter() {
+		// Using log10 to acquire sum instead of multiplying.
+		let mut score = prior.log10();
+		if let Some(word_probs) = p.word_probs.get(label) {
 			for word in doc {
-				if let Some(x) = p.word_spam.get(&word) {
-					// Using log10 to acquire sum instead of multiplying. 
-					spam = spam + x.log10();
-				}
-				if let Some(x) = p.word_legit.get(&word) {
-					// Using log10 to acquire sum instead of multiplying.
-					legit = legit + x.log10();
+				if let Some(x) = word_probs.get(word) {
+					score += x.log10();
 				}
 			}
-
-			// Using log10 to acquire sum instead of multiplying.
-			if (spam + p.spam.log10()) >= (legit+p.legit.log10()) {
-				Ok(true)
-			} else {
-				Ok(false)
-			}
 		}
-		Err(e) => {
-			Err(e)
+		if best.as_ref().is_none_or(|(_, best_score)| score > *best_score) {
+			best = Some((label.clone(), score));
 		}
 	}
+
+	best.map(|(label, _)| label).ok_or_else(|| "model has no trained classes".into())
 }
 
-/// Function that takes a directory and clasifies each mail in it as spam or legit.
-/// Returns spam recall and spam precision.
-fn test_naive_bayes(data_set: Vec<PathBuf>, p: &NaiveBayesProbabilities) -> (f64, f64) {
-	let mut true_positive = 0;
-	let mut false_positive = 0;
-	let mut _true_negative = 0;
-	let mut false_negative = 0;
-	for doc in data_set.iter() {
-		match classified_as_spam(doc, p) {
-			Ok(true) => {
-				if doc.as_path().display().to_string().contains("spmsg") {
-					// Classified as spam and is spam
-					true_positive += 1;
-				}
-				else {
-					// Classified as spam and is legit
-					false_positive += 1;
-				}
+/// Function that takes a filename and the Naive Bayes Probabilities and classifies it,
+///  returning the arg-max class label.
+/// Returns error in case something went wrong.
+fn classify_document(filename: &PathBuf, p: &NaiveBayesProbabilities) -> Result<String, Box<dyn Error>> {
+	let doc = file_to_array(filename)?;
+	classify_ids(&doc, p)
+}
+
+/// Like `classify_document`, but for a raw text mail, encoded with the model's own `Vocabulary`
+///  (words never seen during training are dropped, same as an unknown numeric id).
+fn classify_document_text(filename: &Path, p: &NaiveBayesProbabilities, stop_words: &HashSet<String>) -> Result<String, Box<dyn Error>> {
+	let text = fs::read_to_string(filename)?;
+	let doc: Vec<u32> = tokenize(&text, stop_words).iter().filter_map(|w| p.vocabulary.get(w)).collect();
+	classify_ids(&doc, p)
+}
+
+/// Computes the normalized posterior probability that an already-encoded document is "spam",
+///  in `[0,1]`, using the log-sum-exp trick to avoid underflow: each class's log10-score
+///  `s_c = log10(prior_c) + sum(log10(P(w|c)))` is switched to natural-log space, then
+///  `P(spam) = exp(s_spam - M) / (exp(s_spam - M) + exp(s_legit - M))` where `M = max(s_spam, s_legit)`.
+fn posterior_spam_probability_ids(doc: &[u32], p: &NaiveBayesProbabilities) -> Result<f64, Box<dyn Error>> {
+	let log_score = |label: &str| -> Option<f64> {
+		let mut score = p.priors.get(label)?.log10();
+		let word_probs = p.word_probs.get(label)?;
+		for word in doc {
+			if let Some(x) = word_probs.get(word) {
+				score += x.log10();
 			}
-			Ok(false) => {
-				if doc.as_path().display().to_string().contains("spmsg") {
-					// Classified as legit and is spam
-					false_negative += 1;
-				}
-				else{
-					// Classified as legit and is legit
-					// Variable is not used. Here for completness.
-					_true_negative += 1;
+		}
+		Some(score)
+	};
+
+	let s_spam = log_score("spam").ok_or("model has no \"spam\" class")?;
+	let s_legit = log_score("legit").ok_or("model has no \"legit\" class")?;
+
+	// Switch to natural-log space and subtract the max before exponentiating, to avoid underflow.
+	let (l_spam, l_legit) = (s_spam * std::f64::consts::LN_10, s_legit * std::f64::consts::LN_10);
+	let m = l_spam.max(l_legit);
+	let (e_spam, e_legit) = ((l_spam - m).exp(), (l_legit - m).exp());
+
+	Ok(e_spam / (e_spam + e_legit))
+}
+
+/// Returns the normalized posterior probability that `filename` is "spam", in `[0,1]`, so
+///  callers can apply their own decision threshold instead of a hard-coded `>=` comparison.
+fn posterior_spam_probability(filename: &PathBuf, p: &NaiveBayesProbabilities) -> Result<f64, Box<dyn Error>> {
+	let doc = file_to_array(filename)?;
+	posterior_spam_probability_ids(&doc, p)
+}
+
+/// Like `posterior_spam_probability`, but for a raw text mail, encoded with the model's own
+///  `Vocabulary` the same way `classify_document_text` does.
+fn posterior_spam_probability_text(filename: &Path, p: &NaiveBayesProbabilities, stop_words: &HashSet<String>) -> Result<f64, Box<dyn Error>> {
+	let text = fs::read_to_string(filename)?;
+	let doc: Vec<u32> = tokenize(&text, stop_words).iter().filter_map(|w| p.vocabulary.get(w)).collect();
+	posterior_spam_probability_ids(&doc, p)
+}
+
+/// Function that takes a directory and classifies each mail in it as spam or legit at the given
+///  posterior-probability `threshold` (e.g. 0.8 to only flag mail the model is confident about),
+///  using `posterior` to score each document. Returns spam recall and spam precision.
+///  Classification is spread across the file set in parallel, with the confusion-matrix counts
+///  folded per worker and merged at the end. Shared by `test_naive_bayes` (already-encoded word
+///  ids) and `test_naive_bayes_text` (raw text, tokenized with the model's vocabulary).
+fn test_naive_bayes_with<F>(data_set: Vec<PathBuf>, layout: &CorpusLayout, threshold: f64, posterior: F) -> (f64, f64)
+where F: Fn(&PathBuf) -> Result<f64, Box<dyn Error>> + Sync {
+	let (true_positive, false_positive, false_negative) = data_set.par_iter()
+		.fold(|| (0u32, 0u32, 0u32), |(mut true_positive, mut false_positive, mut false_negative), doc| {
+			let actually_spam = match layout.label_for(doc) {
+				Some(label) => label == "spam",
+				None => { println!("{}: not under a known class directory, skipping", doc.display()); return (true_positive, false_positive, false_negative); }
+			};
+			match posterior(doc) {
+				Ok(p_spam) => {
+					let is_spam = p_spam >= threshold;
+					match (is_spam, actually_spam) {
+						(true, true) => true_positive += 1,
+						(true, false) => false_positive += 1,
+						(false, true) => false_negative += 1,
+						// Here for completness.
+						(false, false) => ()
+					}
 				}
+				Err(e) => println!("{}", e)
 			}
-			Err(e) => println!("{}", e)
-		}
-	}
+			(true_positive, false_positive, false_negative)
+		})
+		.reduce(|| (0, 0, 0), |a, b| (a.0 + b.0, a.1 + b.1, a.2 + b.2));
 
 	(true_positive as f64 / (true_positive + false_negative) as f64,
 	 true_positive as f64 / (true_positive + false_positive) as f64)
 
 }
 
-fn main() {
-	let args: Vec<String> = env::args().collect();
+/// Tests an already-encoded-ids model; see `test_naive_bayes_with`.
+fn test_naive_bayes(data_set: Vec<PathBuf>, p: &NaiveBayesProbabilities, layout: &CorpusLayout, threshold: f64) -> (f64, f64) {
+	test_naive_bayes_with(data_set, layout, threshold, |doc| posterior_spam_probability(doc, p))
+}
 
-	if args.len() == 2 {
-		let path = Path::new(&args[1]);
-		if path.is_dir() {
-			match visit_dirs(path) {
-				Ok(filenames) => {
-					let (_unused,used):(_,Vec<_>)=filenames.into_iter().partition(|x| x.as_path().display().to_string().contains("unused"));
-
-					let mut recall = 0.0;
-					let mut precision = 0.0;
-					for i in 1..11 {
-						println!("Now starting fold number {}", i);
-						//MUST CHANGE TO REFLECT LINUX AND WINDOWS!!!!!!
-						let (test,train):(_,Vec<_>)=used.clone().into_iter().partition(|x| x.as_path().display().to_string().contains(&format!("part{}\\",i)));
-						let probabilities = learn_naive_bayes(train);
-						let (r,p) = test_naive_bayes(test, &probabilities);
-						recall += r;
-						precision += p;
-					}
-					recall = recall / 10.0;
-					precision = precision / 10.0;
-					println!("Spam recall: {}\nSpam precision: {}", recall, precision);
-				},
+/// Like `test_naive_bayes`, but for a model trained on raw text; see `test_naive_bayes_with`.
+fn test_naive_bayes_text(data_set: Vec<PathBuf>, p: &NaiveBayesProbabilities, layout: &CorpusLayout, threshold: f64, stop_words: &HashSet<String>) -> (f64, f64) {
+	test_naive_bayes_with(data_set, layout, threshold, |doc| posterior_spam_probability_text(doc, p, stop_words))
+}
+
+/// Trains a model on every document under `data_set_dir` that isn't under an excluded directory
+///  (per `layout`), and writes it to `model_out` as JSON, so later `classify` runs don't need the
+///  training corpus around. When `text_mode` is set, documents are treated as raw text and
+///  tokenized rather than pre-encoded word ids, dropping any word in `stop_words`.
+fn train(data_set_dir: &Path, model_out: &Path, layout: &CorpusLayout, text_mode: bool, stop_words: &HashSet<String>) {
+	if !data_set_dir.is_dir() {
+		println!("Error: Directory not found!");
+		return;
+	}
+	let scan_options = ScanOptions { excludes: layout.exclude_dirs.clone(), ..ScanOptions::default() };
+	match visit_dirs(data_set_dir, &scan_options) {
+		Ok(used) => {
+			let probabilities = if text_mode {
+				learn_naive_bayes_text(used, layout, stop_words)
+			} else {
+				learn_naive_bayes(used, layout)
+			};
+			match probabilities.save_to_json(model_out) {
+				Ok(()) => println!("Model saved to {}", model_out.display()),
 				Err(e) => println!("{}", e)
-			}		
+			}
+		},
+		Err(e) => println!("{}", e)
+	}
+}
+
+/// Loads a saved model and classifies a single mail file, printing the verdict. When `text_mode`
+///  is set, the mail is treated as raw text and tokenized using the model's own vocabulary,
+///  dropping any word in `stop_words`.
+fn classify(model_path: &Path, mail_path: &Path, text_mode: bool, stop_words: &HashSet<String>) {
+	let probabilities = match NaiveBayesProbabilities::load_from_json(model_path) {
+		Ok(p) => p,
+		Err(e) => { println!("{}", e); return; }
+	};
+	let result = if text_mode {
+		classify_document_text(mail_path, &probabilities, stop_words)
+	} else {
+		classify_document(&mail_path.to_path_buf(), &probabilities)
+	};
+	match result {
+		Ok(label) => println!("{}", label),
+		Err(e) => println!("{}", e)
+	}
+}
+
+/// Runs the original 10-fold cross-validation over `data_set_dir` and prints recall/precision,
+///  classifying mail as spam when its posterior probability is at least `threshold`. Fold and
+///  class directories are identified per `layout`, rather than by substring-matching a literal
+///  Windows-style `partN\` path segment. The 10 folds themselves train and evaluate in parallel.
+/// When `text_mode` is set, documents are treated as raw text, tokenized per fold (each fold's
+///  training split interns its own `Vocabulary`, same as `train` would), dropping any word in
+///  `stop_words`. This is what makes already-tokenized corpora like Enron-Spam usable for CV.
+fn eval(data_set_dir: &Path, layout: &CorpusLayout, threshold: f64, text_mode: bool, stop_words: &HashSet<String>) {
+	if !data_set_dir.is_dir() {
+		println!("Error: Directory not found!");
+		return;
+	}
+	let scan_options = ScanOptions { excludes: layout.exclude_dirs.clone(), ..ScanOptions::default() };
+	match visit_dirs(data_set_dir, &scan_options) {
+		Ok(used) => {
+			let (recall, precision) = (1..11).into_par_iter()
+				.map(|i| {
+					println!("Now starting fold number {}", i);
+					let (test, train): (_, Vec<_>) = used.clone().into_iter().partition(|x| layout.in_fold(x, i));
+					if text_mode {
+						let probabilities = learn_naive_bayes_text(train, layout, stop_words);
+						test_naive_bayes_text(test, &probabilities, layout, threshold, stop_words)
+					} else {
+						let probabilities = learn_naive_bayes(train, layout);
+						test_naive_bayes(test, &probabilities, layout, threshold)
+					}
+				})
+				.reduce(|| (0.0, 0.0), |a, b| (a.0 + b.0, a.1 + b.1));
+
+			println!("Spam recall: {}\nSpam precision: {}", recall / 10.0, precision / 10.0);
+		},
+		Err(e) => println!("{}", e)
+	}
+}
+
+/// The `--text` and `--stop-words <file>` flags, plus whatever positional arguments are left
+///  after pulling them out, in the order they appeared. Flags can appear anywhere after the
+///  subcommand name, not just at a fixed position.
+struct ParsedArgs {
+	positional: Vec<String>,
+	text_mode: bool,
+	stop_words_path: Option<PathBuf>
+}
+
+fn parse_args(args: &[String]) -> ParsedArgs {
+	let mut positional = Vec::new();
+	let mut text_mode = false;
+	let mut stop_words_path = None;
+
+	let mut i = 0;
+	while i < args.len() {
+		match args[i].as_str() {
+			"--text" => { text_mode = true; i += 1; },
+			"--stop-words" => {
+				match args.get(i + 1) {
+					Some(value) if !value.starts_with("--") => { stop_words_path = Some(PathBuf::from(value)); i += 2; },
+					_ => { i += 1; }
+				}
+			},
+			other => { positional.push(other.to_string()); i += 1; }
 		}
-		else {
-			println!("Error: Directory not found!")
+	}
+
+	ParsedArgs { positional, text_mode, stop_words_path }
+}
+
+/// Reads one stop word per line from `path`, lower-cased and trimmed to match `tokenize`'s output.
+fn load_stop_words_file(path: &Path) -> Result<HashSet<String>, Box<dyn Error>> {
+	let text = fs::read_to_string(path)?;
+	Ok(text.lines()
+		.map(|w| w.trim().to_lowercase())
+		.filter(|w| !w.is_empty())
+		.collect())
+}
+
+/// Resolves `--stop-words <file>` to its contents, or an empty set if the flag wasn't given.
+fn load_stop_words(path: Option<&Path>) -> HashSet<String> {
+	match path {
+		None => HashSet::new(),
+		Some(path) => match load_stop_words_file(path) {
+			Ok(words) => words,
+			Err(e) => { println!("{}", e); HashSet::new() }
 		}
-	} else {
-		println!("arg1: dataset directory");
+	}
+}
+
+fn print_usage() {
+	println!("Usage:");
+	println!("  train <dataset_dir> <model_out.json> [flags]   Train a model and save it to disk");
+	println!("  classify <model.json> <mail_file> [flags]      Classify a single mail using a saved model");
+	println!("  eval <dataset_dir> [threshold] [flags]          Run 10-fold cross-validation over the dataset");
+	println!("                                                   (spam posterior threshold, default 0.5)");
+	println!();
+	println!("Flags (train, classify and eval all accept them):");
+	println!("  --text                  treats documents as raw text, tokenized with the crate's");
+	println!("                           own vocabulary, instead of corpora already encoded into");
+	println!("                           numeric word ids");
+	println!("  --stop-words <file>     drops words listed one per line in <file> during");
+	println!("                           tokenization; only takes effect together with --text");
+}
+
+fn main() {
+	let args: Vec<String> = env::args().collect();
+	let command = args.get(1).map(String::as_str);
+	let parsed = parse_args(args.get(2..).unwrap_or(&[]));
+
+	match command {
+		Some("train") if parsed.positional.len() == 2 => {
+			let stop_words = load_stop_words(parsed.stop_words_path.as_deref());
+			train(Path::new(&parsed.positional[0]), Path::new(&parsed.positional[1]), &CorpusLayout::default(), parsed.text_mode, &stop_words)
+		},
+		Some("classify") if parsed.positional.len() == 2 => {
+			let stop_words = load_stop_words(parsed.stop_words_path.as_deref());
+			classify(Path::new(&parsed.positional[0]), Path::new(&parsed.positional[1]), parsed.text_mode, &stop_words)
+		},
+		Some("eval") if parsed.positional.len() == 1 || parsed.positional.len() == 2 => {
+			let threshold = parsed.positional.get(1).map(|t| t.parse().unwrap_or(0.5)).unwrap_or(0.5);
+			let stop_words = load_stop_words(parsed.stop_words_path.as_deref());
+			eval(Path::new(&parsed.positional[0]), &CorpusLayout::default(), threshold, parsed.text_mode, &stop_words)
+		},
+		_ => print_usage()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn save_to_json_then_load_from_json_round_trips() {
+		let mut word_probs: HashMap<u32, f64, BuildIdentityHasher> = HashMap::default();
+		word_probs.insert(1, 0.25);
+		word_probs.insert(2, 0.75);
+
+		let original = NaiveBayesProbabilities {
+			priors: HashMap::from([("spam".to_string(), 0.4), ("legit".to_string(), 0.6)]),
+			word_probs: HashMap::from([("spam".to_string(), word_probs)]),
+			vocabulary: Vocabulary::default()
+		};
+
+		let path = std::env::temp_dir().join("knaive_bayes_model_round_trip_test.json");
+		original.save_to_json(&path).expect("save_to_json should succeed");
+		let reloaded = NaiveBayesProbabilities::load_from_json(&path).expect("load_from_json should succeed");
+		fs::remove_file(&path).ok();
+
+		assert_eq!(reloaded.priors, original.priors);
+		assert_eq!(reloaded.word_probs.get("spam").unwrap().get(&1), Some(&0.25));
+		assert_eq!(reloaded.word_probs.get("spam").unwrap().get(&2), Some(&0.75));
+	}
+
+	/// Builds a model with three classes, each overwhelmingly likely to produce one particular
+	///  word, so `classify_ids` picking the arg-max class can be checked against an unambiguous
+	///  expectation.
+	fn three_class_model() -> NaiveBayesProbabilities {
+		let mut priors = HashMap::new();
+		let mut word_probs = HashMap::new();
+		for (label, likely_word) in [("spam", 1u32), ("legit", 2u32), ("promo", 3u32)] {
+			priors.insert(label.to_string(), 1.0 / 3.0);
+			let mut probs: HashMap<u32, f64, BuildIdentityHasher> = HashMap::default();
+			for word in 1..=3u32 {
+				probs.insert(word, if word == likely_word { 0.96 } else { 0.02 });
+			}
+			word_probs.insert(label.to_string(), probs);
+		}
+		NaiveBayesProbabilities { priors, word_probs, vocabulary: Vocabulary::default() }
+	}
+
+	#[test]
+	fn classify_ids_picks_the_arg_max_class_among_more_than_two() {
+		let model = three_class_model();
+		assert_eq!(classify_ids(&[1, 1, 1], &model).unwrap(), "spam");
+		assert_eq!(classify_ids(&[2, 2, 2], &model).unwrap(), "legit");
+		assert_eq!(classify_ids(&[3, 3, 3], &model).unwrap(), "promo");
+	}
+
+	#[test]
+	fn posterior_spam_probability_ids_matches_hand_computed_bayes_rule() {
+		// Equal priors and a single word, so the log-sum-exp result should reduce to plain
+		// Bayes' rule: P(spam|w) = P(w|spam)P(spam) / (P(w|spam)P(spam) + P(w|legit)P(legit))
+		//            = 0.8*0.5 / (0.8*0.5 + 0.2*0.5) = 0.8
+		let mut spam_probs: HashMap<u32, f64, BuildIdentityHasher> = HashMap::default();
+		spam_probs.insert(1, 0.8);
+		let mut legit_probs: HashMap<u32, f64, BuildIdentityHasher> = HashMap::default();
+		legit_probs.insert(1, 0.2);
+
+		let model = NaiveBayesProbabilities {
+			priors: HashMap::from([("spam".to_string(), 0.5), ("legit".to_string(), 0.5)]),
+			word_probs: HashMap::from([("spam".to_string(), spam_probs), ("legit".to_string(), legit_probs)]),
+			vocabulary: Vocabulary::default()
+		};
+
+		let p_spam = posterior_spam_probability_ids(&[1], &model).unwrap();
+		assert!((p_spam - 0.8).abs() < 1e-9, "expected ~0.8, got {}", p_spam);
+	}
+
+	#[test]
+	fn counts_merge_is_equivalent_regardless_of_how_the_data_set_is_split() {
+		// Rayon's fold/reduce may split `docs` across workers in any grouping; merging those
+		// partial accumulators must produce the same counts as folding everything sequentially.
+		let layout = CorpusLayout::default();
+		let docs: Vec<(PathBuf, Vec<u32>)> = vec![
+			(PathBuf::from("corpus/spam/1.txt"), vec![1, 1, 2]),
+			(PathBuf::from("corpus/spam/2.txt"), vec![1, 3]),
+			(PathBuf::from("corpus/legit/1.txt"), vec![2, 4]),
+			(PathBuf::from("corpus/legit/2.txt"), vec![4])
+		];
+
+		let fold_all = |set: &[(PathBuf, Vec<u32>)]| {
+			set.iter().fold(Counts::new(), |acc, (doc, example)| acc.add(doc, example, &layout))
+		};
+
+		let sequential = fold_all(&docs);
+		let (first_half, second_half) = docs.split_at(2);
+		let merged = fold_all(first_half).merge(fold_all(second_half));
+
+		assert_eq!(sequential.total, merged.total);
+		assert_eq!(sequential.class_counts, merged.class_counts);
+		assert_eq!(sequential.class_token_counts, merged.class_token_counts);
+		assert_eq!(sequential.word_set, merged.word_set);
+		assert_eq!(sequential.occurences, merged.occurences);
+	}
+
+	#[test]
+	fn posterior_spam_probability_text_matches_the_ids_version_through_the_vocabulary() {
+		let mut vocabulary = Vocabulary::new();
+		let free = vocabulary.intern("free");
+
+		let mut spam_probs: HashMap<u32, f64, BuildIdentityHasher> = HashMap::default();
+		spam_probs.insert(free, 0.8);
+		let mut legit_probs: HashMap<u32, f64, BuildIdentityHasher> = HashMap::default();
+		legit_probs.insert(free, 0.2);
+
+		let model = NaiveBayesProbabilities {
+			priors: HashMap::from([("spam".to_string(), 0.5), ("legit".to_string(), 0.5)]),
+			word_probs: HashMap::from([("spam".to_string(), spam_probs), ("legit".to_string(), legit_probs)]),
+			vocabulary
+		};
+
+		let path = std::env::temp_dir().join("knaive_bayes_posterior_text_test.txt");
+		fs::write(&path, "Free!!").unwrap();
+		let p_spam = posterior_spam_probability_text(&path, &model, &HashSet::new()).unwrap();
+		fs::remove_file(&path).ok();
+
+		assert!((p_spam - 0.8).abs() < 1e-9, "expected ~0.8, got {}", p_spam);
+	}
+
+	#[test]
+	fn parse_args_pulls_text_and_stop_words_flags_out_of_any_position() {
+		let args: Vec<String> = vec!["corpus/".to_string(), "--stop-words".to_string(), "stop.txt".to_string(), "--text".to_string(), "0.8".to_string()]
+			.into_iter().collect();
+		let parsed = parse_args(&args);
+
+		assert_eq!(parsed.positional, vec!["corpus/".to_string(), "0.8".to_string()]);
+		assert!(parsed.text_mode);
+		assert_eq!(parsed.stop_words_path, Some(PathBuf::from("stop.txt")));
+	}
+
+	#[test]
+	fn parse_args_does_not_swallow_an_adjacent_flag_as_the_stop_words_filename() {
+		let args: Vec<String> = vec!["corpus/".to_string(), "--stop-words".to_string(), "--text".to_string()]
+			.into_iter().collect();
+		let parsed = parse_args(&args);
+
+		assert_eq!(parsed.positional, vec!["corpus/".to_string()]);
+		assert!(parsed.text_mode);
+		assert_eq!(parsed.stop_words_path, None);
+	}
+
+	#[test]
+	fn load_stop_words_file_lowercases_and_trims_each_line() {
+		let path = std::env::temp_dir().join("knaive_bayes_stop_words_test.txt");
+		fs::write(&path, "The\n  Of \n\nAND\n").unwrap();
+		let stop_words = load_stop_words_file(&path).unwrap();
+		fs::remove_file(&path).ok();
+
+		assert_eq!(stop_words, vec!["the".to_string(), "of".to_string(), "and".to_string()].into_iter().collect());
 	}
 }
\ No newline at end of file
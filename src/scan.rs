@@ -0,0 +1,148 @@
+//! Options controlling how `visit_dirs` walks a corpus directory: which directory names to
+//!  prune entirely, whether to skip hidden entries, and whether to follow symlinks.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Configures a directory walk. Defaults are chosen to be safe on an unfamiliar tree: hidden
+///  entries are skipped and symlinks aren't followed, so a stray `.git` directory or a symlink
+///  cycle can't derail a scan.
+pub struct ScanOptions {
+	/// Directory names to prune entirely, without descending into them.
+	pub excludes: HashSet<String>,
+	/// Skip entries whose file name starts with `.`.
+	pub ignore_hidden: bool,
+	/// Descend into symlinked directories instead of skipping them.
+	pub follow_symlinks: bool
+}
+
+impl Default for ScanOptions {
+	fn default() -> Self {
+		ScanOptions {
+			excludes: HashSet::new(),
+			ignore_hidden: true,
+			follow_symlinks: false
+		}
+	}
+}
+
+/// Recursive function to traverse the directorirs below a directory in a filesystem, pruning
+///  entries per `options` (excluded directory names, hidden entries, symlinked directories) as it
+///  goes rather than collecting everything and filtering afterwards. Symlinked regular files are
+///  still collected regardless of `follow_symlinks`, since including a single file can't create a
+///  cycle; only symlinked directories are skipped (and logged) when `follow_symlinks` is false.
+/// Returns error in case something went wrong.
+pub fn visit_dirs(dir: &Path, options: &ScanOptions) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+	let mut ret = Vec::new();
+	if dir.is_dir() {
+		for entry in fs::read_dir(dir)? {
+			let entry = entry?;
+			let path = entry.path();
+			let name = entry.file_name();
+			let name = name.to_string_lossy();
+
+			if options.ignore_hidden && name.starts_with('.') {
+				continue;
+			}
+			if options.excludes.contains(name.as_ref()) {
+				continue;
+			}
+
+			if path.is_dir() {
+				if entry.file_type()?.is_symlink() && !options.follow_symlinks {
+					println!("{}: symlinked directory, skipping to avoid cycles", path.display());
+					continue;
+				}
+				ret.append(&mut visit_dirs(&path, options)?);
+			} else {
+				ret.push(path);
+			}
+		}
+	}
+	Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::os::unix::fs::symlink;
+
+	/// Builds a unique scratch directory under the OS temp dir, removed again by the caller.
+	fn scratch_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("knaive_bayes_scan_test_{}", name));
+		fs::remove_dir_all(&dir).ok();
+		fs::create_dir_all(&dir).unwrap();
+		dir
+	}
+
+	#[test]
+	fn visit_dirs_prunes_excluded_directories() {
+		let root = scratch_dir("excludes");
+		fs::create_dir_all(root.join("unused")).unwrap();
+		fs::write(root.join("unused/1.txt"), "1").unwrap();
+		fs::write(root.join("kept.txt"), "1").unwrap();
+
+		let options = ScanOptions { excludes: vec!["unused".to_string()].into_iter().collect(), ..ScanOptions::default() };
+		let found = visit_dirs(&root, &options).unwrap();
+
+		fs::remove_dir_all(&root).ok();
+		assert_eq!(found, vec![root.join("kept.txt")]);
+	}
+
+	#[test]
+	fn visit_dirs_skips_hidden_entries_by_default() {
+		let root = scratch_dir("hidden");
+		fs::write(root.join(".hidden.txt"), "1").unwrap();
+		fs::write(root.join("visible.txt"), "1").unwrap();
+
+		let found = visit_dirs(&root, &ScanOptions::default()).unwrap();
+
+		fs::remove_dir_all(&root).ok();
+		assert_eq!(found, vec![root.join("visible.txt")]);
+	}
+
+	#[test]
+	fn visit_dirs_does_not_descend_into_symlinked_directories_by_default() {
+		let root = scratch_dir("symlink_dir");
+		let real = scratch_dir("symlink_dir_target");
+		fs::write(real.join("inside.txt"), "1").unwrap();
+		symlink(&real, root.join("linked")).unwrap();
+
+		let found = visit_dirs(&root, &ScanOptions::default()).unwrap();
+
+		fs::remove_dir_all(&root).ok();
+		fs::remove_dir_all(&real).ok();
+		assert_eq!(found, Vec::<PathBuf>::new());
+	}
+
+	#[test]
+	fn visit_dirs_descends_into_symlinked_directories_when_follow_symlinks_is_set() {
+		let root = scratch_dir("symlink_dir_follow");
+		let real = scratch_dir("symlink_dir_follow_target");
+		fs::write(real.join("inside.txt"), "1").unwrap();
+		symlink(&real, root.join("linked")).unwrap();
+
+		let options = ScanOptions { follow_symlinks: true, ..ScanOptions::default() };
+		let found = visit_dirs(&root, &options).unwrap();
+
+		fs::remove_dir_all(&root).ok();
+		fs::remove_dir_all(&real).ok();
+		assert_eq!(found, vec![root.join("linked").join("inside.txt")]);
+	}
+
+	#[test]
+	fn visit_dirs_collects_symlinked_files_regardless_of_follow_symlinks() {
+		let root = scratch_dir("symlink_file");
+		fs::write(root.join("real.txt"), "1").unwrap();
+		symlink(root.join("real.txt"), root.join("linked.txt")).unwrap();
+
+		let found = visit_dirs(&root, &ScanOptions::default()).unwrap();
+
+		fs::remove_dir_all(&root).ok();
+		let mut found = found;
+		found.sort();
+		assert_eq!(found, vec![root.join("linked.txt"), root.join("real.txt")]);
+	}
+}
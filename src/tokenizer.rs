@@ -0,0 +1,71 @@
+//! Tokenization subsystem so the crate can ingest raw text mail instead of requiring a corpus
+//!  that has already been encoded into numeric word ids (as the PU123A corpora are).
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use serde::{Serialize, Deserialize};
+
+/// Maps each distinct word to a stable `u32` id, reused for the identity-hashed maps elsewhere
+///  in the crate. Persisted alongside a trained model so classification encodes new documents
+///  the same way they were encoded during training.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Vocabulary {
+	word_to_id: HashMap<String, u32>
+}
+
+impl Vocabulary {
+	pub fn new() -> Self {
+		Vocabulary { word_to_id: HashMap::new() }
+	}
+
+	/// Looks up the id for `word`, interning a new one if it hasn't been seen before.
+	pub fn intern(&mut self, word: &str) -> u32 {
+		if let Some(&id) = self.word_to_id.get(word) {
+			id
+		} else {
+			let id = self.word_to_id.len() as u32;
+			self.word_to_id.insert(word.to_string(), id);
+			id
+		}
+	}
+
+	/// Looks up the id for `word` without creating one; used at classification time, where a
+	///  word that was never seen during training simply doesn't contribute to the score.
+	pub fn get(&self, word: &str) -> Option<u32> {
+		self.word_to_id.get(word).copied()
+	}
+}
+
+/// Splits `text` into lowercase tokens, treating any run of non-alphanumeric characters as a
+///  separator, and drops any token present in `stop_words`.
+pub fn tokenize(text: &str, stop_words: &HashSet<String>) -> Vec<String> {
+	text.to_lowercase()
+		.split(|c: char| !c.is_alphanumeric())
+		.filter(|w| !w.is_empty())
+		.filter(|w| !stop_words.contains(*w))
+		.map(|w| w.to_string())
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tokenize_lowercases_splits_on_punctuation_and_drops_stop_words() {
+		let stop_words: HashSet<String> = vec!["the".to_string()].into_iter().collect();
+		let tokens = tokenize("Free Money!! Click-here, the offer expires.", &stop_words);
+		assert_eq!(tokens, vec!["free", "money", "click", "here", "offer", "expires"]);
+	}
+
+	#[test]
+	fn vocabulary_interns_each_distinct_word_once_and_reuses_its_id() {
+		let mut vocabulary = Vocabulary::new();
+		let first = vocabulary.intern("free");
+		let second = vocabulary.intern("money");
+		assert_eq!(vocabulary.intern("free"), first);
+		assert_ne!(first, second);
+		assert_eq!(vocabulary.get("free"), Some(first));
+		assert_eq!(vocabulary.get("never seen"), None);
+	}
+}